@@ -0,0 +1,207 @@
+use crate::index::Posting;
+
+/// Magic bytes identifying a LinScan compressed index file (format version 1).
+const MAGIC: &[u8; 4] = b"LSC1";
+
+/// Encodes a posting list (already sorted in ascending `docid` order, as guaranteed by
+/// sequential `insert`) into the compressed on-disk representation: a run of varint-encoded
+/// ascending docid deltas, followed by a parallel array of the raw LE `f32` values. Keeping the
+/// values in their own contiguous region (rather than interleaved with the docids) lets a reader
+/// skip straight to them, or to the docids alone, without decoding the other.
+pub fn encode_posting_list(postings: &[Posting]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(postings.len() * 5);
+
+    let mut prev_docid = 0_u32;
+    for posting in postings {
+        varint_encode((posting.docid - prev_docid) as u64, &mut buf);
+        prev_docid = posting.docid;
+    }
+    for posting in postings {
+        buf.extend_from_slice(&posting.value.to_le_bytes());
+    }
+
+    buf
+}
+
+/// Decodes a block produced by `encode_posting_list` back into a `Vec<Posting>`. `count` must be
+/// the number of postings the block was encoded with (recorded alongside it in the offset table).
+pub fn decode_posting_list(bytes: &[u8], count: usize) -> Vec<Posting> {
+    PostingListDecoder::new(bytes, count).collect()
+}
+
+/// A lazy, decode-on-demand iterator over an encoded posting-list block. Unlike
+/// `decode_posting_list`, this never materializes the whole list: each call to `next()` decodes
+/// exactly one docid delta and reads one value out of the parallel value array, so callers that
+/// only need to walk part of a list don't pay to decode postings they never look at.
+pub struct PostingListDecoder<'a> {
+    bytes: &'a [u8],
+    docid_pos: usize,
+    values_offset: usize,
+    prev_docid: u32,
+    index: usize,
+    count: usize,
+}
+
+impl<'a> PostingListDecoder<'a> {
+    pub fn new(bytes: &'a [u8], count: usize) -> PostingListDecoder<'a> {
+        PostingListDecoder {
+            bytes,
+            docid_pos: 0,
+            values_offset: bytes.len() - count * 4,
+            prev_docid: 0,
+            index: 0,
+            count,
+        }
+    }
+}
+
+impl<'a> Iterator for PostingListDecoder<'a> {
+    type Item = Posting;
+
+    fn next(&mut self) -> Option<Posting> {
+        if self.index >= self.count {
+            return None;
+        }
+
+        let (delta, consumed) = varint_decode(&self.bytes[self.docid_pos..]);
+        self.docid_pos += consumed;
+        self.prev_docid += delta as u32;
+
+        let value_start = self.values_offset + self.index * 4;
+        let value = f32::from_le_bytes(
+            self.bytes[value_start..value_start + 4].try_into().unwrap(),
+        );
+
+        self.index += 1;
+        Some(Posting { docid: self.prev_docid, value })
+    }
+}
+
+/// Encodes `value` as a LEB128-style variable-length byte sequence: seven bits of payload per
+/// byte, with the high bit set on every byte but the last. Small deltas (the common case in a
+/// posting list sorted by docid) cost a single byte.
+pub fn varint_encode(mut value: u64, buf: &mut Vec<u8>) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        buf.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// Decodes a varint written by `varint_encode` from the start of `bytes`, returning the decoded
+/// value and the number of bytes consumed.
+pub fn varint_decode(bytes: &[u8]) -> (u64, usize) {
+    let mut value = 0_u64;
+    let mut shift = 0;
+    let mut consumed = 0;
+    loop {
+        let byte = bytes[consumed];
+        consumed += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    (value, consumed)
+}
+
+/// Optionally zstd-compresses an encoded posting-list block before it is written to disk.
+pub fn maybe_compress(raw: &[u8], use_zstd: bool) -> Vec<u8> {
+    if use_zstd {
+        zstd::encode_all(raw, 0).expect("zstd compression failed")
+    } else {
+        raw.to_vec()
+    }
+}
+
+/// Reverses `maybe_compress`, given the expected decompressed length.
+pub fn maybe_decompress(stored: &[u8], raw_len: u64, use_zstd: bool) -> Vec<u8> {
+    if use_zstd {
+        let decoded = zstd::decode_all(stored).expect("zstd decompression failed");
+        debug_assert_eq!(decoded.len() as u64, raw_len);
+        decoded
+    } else {
+        stored.to_vec()
+    }
+}
+
+pub fn write_magic(buf: &mut Vec<u8>) {
+    buf.extend_from_slice(MAGIC);
+}
+
+pub fn check_magic(bytes: &[u8]) {
+    assert_eq!(&bytes[0..4], MAGIC, "not a LinScan compressed index file");
+}
+
+/// Size in bytes of the fixed header written by `Index::save_compressed`: magic, `num_docs`, the
+/// number of posting lists, the zstd flag, the byte length of the posting-list region (needed to
+/// find where it ends and the tombstone section begins), and the number of tombstoned docids.
+pub const HEADER_LEN: usize = 4 + 4 + 4 + 1 + 8 + 4;
+
+/// Size in bytes of a single offset-table entry: coordinate, count, max_abs_value, offset,
+/// stored_len, raw_len.
+pub const ENTRY_LEN: usize = 4 + 4 + 4 + 8 + 8 + 8;
+
+/// One parsed entry of the on-disk offset table: everything needed to find and decode a single
+/// coordinate's posting-list block within the posting-list region, without having read the block
+/// itself yet.
+pub struct OffsetEntry {
+    pub coordinate: u32,
+    pub count: u32,
+    pub max_abs_value: f32,
+    pub offset: u64,
+    pub stored_len: u64,
+    pub raw_len: u64,
+}
+
+/// Parses the fixed-size header at the start of a `save_compressed` file, returning `num_docs`,
+/// the number of posting lists, whether blocks are zstd-compressed, the byte length of the
+/// posting-list region, and the number of tombstoned docids stored after it.
+pub fn parse_header(bytes: &[u8]) -> (u32, usize, bool, u64, u32) {
+    check_magic(bytes);
+    let num_docs = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    let num_lists = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+    let use_zstd = bytes[12] != 0;
+    let region_len = u64::from_le_bytes(bytes[13..21].try_into().unwrap());
+    let num_tombstones = u32::from_le_bytes(bytes[21..25].try_into().unwrap());
+    (num_docs, num_lists, use_zstd, region_len, num_tombstones)
+}
+
+/// Parses the tombstone section written immediately after the posting-list region: a flat run of
+/// `num_tombstones` little-endian `u32` docids.
+pub fn parse_tombstones(bytes: &[u8], tombstones_start: usize, num_tombstones: u32) -> Vec<u32> {
+    let mut tombstones = Vec::with_capacity(num_tombstones as usize);
+    for i in 0..num_tombstones as usize {
+        let entry = &bytes[tombstones_start + i * 4..tombstones_start + i * 4 + 4];
+        tombstones.push(u32::from_le_bytes(entry.try_into().unwrap()));
+    }
+    tombstones
+}
+
+/// Parses the `num_lists` offset-table entries immediately following the header. Returns the
+/// parsed entries alongside the byte offset at which the posting-list region begins, i.e. where
+/// block offsets in those entries are relative to.
+pub fn parse_offset_table(bytes: &[u8], num_lists: usize) -> (Vec<OffsetEntry>, usize) {
+    let region_start = HEADER_LEN + num_lists * ENTRY_LEN;
+
+    let mut entries = Vec::with_capacity(num_lists);
+    for i in 0..num_lists {
+        let entry = &bytes[HEADER_LEN + i * ENTRY_LEN..HEADER_LEN + (i + 1) * ENTRY_LEN];
+        entries.push(OffsetEntry {
+            coordinate: u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+            count: u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+            max_abs_value: f32::from_le_bytes(entry[8..12].try_into().unwrap()),
+            offset: u64::from_le_bytes(entry[12..20].try_into().unwrap()),
+            stored_len: u64::from_le_bytes(entry[20..28].try_into().unwrap()),
+            raw_len: u64::from_le_bytes(entry[28..36].try_into().unwrap()),
+        });
+    }
+    (entries, region_start)
+}
@@ -1,10 +1,11 @@
 use std::cmp::{Ordering, Reverse};
 use std::cmp::Ordering::Equal;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::time::{Duration, Instant};
 use std::fmt;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read, Write};
 use serde::{Serialize, Deserialize};
+use crate::compressed;
 
 /// A structure that reports the outcome of the inner product computation for a single document.
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -34,19 +35,132 @@ pub struct Posting {
     pub value: f32,
 }
 
+/// A seekable cursor over a single coordinate's posting list, used by `retrieve_wand` to
+/// implement document-at-a-time traversal. Modeled after tantivy's `DocSet`: the cursor tracks
+/// its current position and can be advanced with `skip_to`, relying on the list being sorted in
+/// ascending `docid` order.
+struct PostingCursor<'a> {
+    query_value: f32,
+    /// Upper bound on the contribution this coordinate can make to any document's score,
+    /// i.e. `|query_value| * list_max`.
+    max_contribution: f32,
+    postings: &'a [Posting],
+    pos: usize,
+}
+
+impl<'a> PostingCursor<'a> {
+    fn new(postings: &'a [Posting], query_value: f32, list_max: f32) -> PostingCursor<'a> {
+        PostingCursor {
+            query_value,
+            max_contribution: query_value.abs() * list_max,
+            postings,
+            pos: 0,
+        }
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.pos >= self.postings.len()
+    }
+
+    fn docid(&self) -> u32 {
+        self.postings[self.pos].docid
+    }
+
+    fn value(&self) -> f32 {
+        self.postings[self.pos].value
+    }
+
+    /// Advances the cursor to the first posting with `docid >= target`. The remaining slice is
+    /// sorted by `docid`, so this is a binary search rather than a linear walk.
+    fn skip_to(&mut self, target: u32) {
+        if self.is_exhausted() || self.docid() >= target {
+            return;
+        }
+        let remaining = &self.postings[self.pos..];
+        self.pos += remaining.partition_point(|p| p.docid < target);
+    }
+}
+
 /// Vanilla LinScan operates on an uncompressed inverted index.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Index {
     inverted_index: HashMap<u32, Vec<Posting>>,
+    /// The maximum absolute `Posting.value` seen in each coordinate's posting list, kept in sync
+    /// with `inverted_index` on every `insert`. This is an upper bound on the contribution any
+    /// single posting in the list can make to a dot product, regardless of the sign of the query
+    /// value, and is what `retrieve_wand` uses to compute pruning bounds.
+    list_max: HashMap<u32, f32>,
     num_docs: u32,
+    /// Docids that have been `delete`d (or superseded by an `update`). `retrieve` and
+    /// `retrieve_wand` consult this to skip them rather than physically removing their postings;
+    /// `compact` is what actually rewrites the posting lists to drop them.
+    tombstones: HashSet<u32>,
+    /// The L2 norm of each document's stored values, indexed by docid, captured at `insert` time.
+    /// This is the "document length" term BM25 scoring normalizes term frequency against.
+    doc_length: Vec<f32>,
+    /// The number of documents each coordinate appears in, kept in sync with `inverted_index` on
+    /// every `insert`. This is BM25's document frequency term.
+    doc_freq: HashMap<u32, u32>,
+}
+
+/// Selects how `retrieve` turns a query/document pair of postings into a score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoringMode {
+    /// Plain sparse inner product: `sum(query_value * posting.value)`.
+    InnerProduct,
+    /// Okapi BM25, treating each coordinate's stored value as a term frequency and `doc_length`
+    /// as the document length `b` normalizes against. `k1` and `b` are the usual BM25 knobs.
+    Bm25 { k1: f32, b: f32 },
+}
+
+/// BM25 scoring parameters threaded through `compute_bm25`, bundled into one struct to keep that
+/// function's argument count down. `k1` and `b` come straight from `ScoringMode::Bm25`;
+/// `avg_doc_length` is the corpus-wide average of `doc_length`, computed once per `retrieve` call
+/// rather than recomputed for every coordinate.
+struct Bm25Params {
+    k1: f32,
+    b: f32,
+    avg_doc_length: f32,
 }
 
 impl Index {
     pub fn new() -> Index {
         Index {
             inverted_index: HashMap::new(),
+            list_max: HashMap::new(),
             num_docs: 0,
+            tombstones: HashSet::new(),
+            doc_length: Vec::new(),
+            doc_freq: HashMap::new(),
+        }
+    }
+
+    /// The number of documents inserted into this index so far.
+    pub fn num_docs(&self) -> u32 {
+        self.num_docs
+    }
+
+    /// Merges all of `other`'s documents into `self`, shifting every docid by `docid_offset`.
+    /// Used by `SegmentedIndex::merge` to k-way merge segment posting lists into one contiguous
+    /// segment: each coordinate's combined list stays sorted in ascending docid order as long as
+    /// segments are absorbed in the same order they were originally flushed in, i.e. with
+    /// strictly increasing offsets.
+    pub(crate) fn absorb(&mut self, other: &Index, docid_offset: u32) {
+        for (&coordinate, postings) in &other.inverted_index {
+            let list = self.inverted_index.entry(coordinate).or_default();
+            for posting in postings {
+                list.push(Posting { docid: posting.docid + docid_offset, value: posting.value });
+            }
+            let other_max = *other.list_max.get(&coordinate).unwrap_or(&0_f32);
+            let max = self.list_max.entry(coordinate).or_insert(0_f32);
+            if other_max > *max {
+                *max = other_max;
+            }
+            let other_df = *other.doc_freq.get(&coordinate).unwrap_or(&0);
+            *self.doc_freq.entry(coordinate).or_insert(0) += other_df;
         }
+        self.doc_length.extend_from_slice(&other.doc_length);
+        self.num_docs += other.num_docs;
     }
 
     /// Inserts a new document into the index.
@@ -59,60 +173,254 @@ impl Index {
                 docid: self.num_docs,
                 value,
             });
+            let max = self.list_max.entry(coordinate).or_insert(0_f32);
+            if value.abs() > *max {
+                *max = value.abs();
+            }
+            *self.doc_freq.entry(coordinate).or_insert(0) += 1;
         }
+        let length = document.values().map(|v| v * v).sum::<f32>().sqrt();
+        self.doc_length.push(length);
         self.num_docs += 1;
     }
 
-    fn compute_dot_product(&self, coordinate: u32, query_value: f32, scores: &mut [f32]) {
+    /// Marks `docid` as deleted. The document's postings are left in place on disk/in memory;
+    /// `retrieve` and `retrieve_wand` simply skip the docid from here on. Use `compact` to
+    /// physically reclaim the space once enough documents have been deleted.
+    pub fn delete(&mut self, docid: u32) {
+        self.tombstones.insert(docid);
+    }
+
+    /// Replaces the document at `docid` with `document`. Since postings aren't indexed by docid
+    /// (only the other way around), updating in place would mean scanning every posting list for
+    /// the old entries; instead this deletes `docid` and inserts `document` as a fresh document,
+    /// returning its new docid. Callers that need `docid` to stay stable should keep track of the
+    /// returned id themselves (e.g. in their own document metadata).
+    pub fn update(&mut self, docid: u32, document: &HashMap<u32, f32>) -> u32 {
+        self.delete(docid);
+        let new_docid = self.num_docs;
+        self.insert(document);
+        new_docid
+    }
+
+    /// Physically rewrites every posting list to drop tombstoned postings, then renumbers the
+    /// surviving docids contiguously starting from 0 (preserving their relative order). Returns a
+    /// map from old docid to new docid so callers can update any metadata keyed by docid.
+    pub fn compact(&mut self) -> HashMap<u32, u32> {
+        let mut remap = HashMap::new();
+        let mut next_docid = 0_u32;
+        for old_docid in 0..self.num_docs {
+            if !self.tombstones.contains(&old_docid) {
+                remap.insert(old_docid, next_docid);
+                next_docid += 1;
+            }
+        }
+
+        // `doc_length` is indexed positionally by docid, so it has to be renumbered in lockstep
+        // with the postings below, not just truncated.
+        let mut doc_length = vec![0_f32; next_docid as usize];
+        for (&old_docid, &new_docid) in &remap {
+            doc_length[new_docid as usize] = self.doc_length[old_docid as usize];
+        }
+
+        let mut empty_coordinates = Vec::new();
+        for (&coordinate, postings) in self.inverted_index.iter_mut() {
+            postings.retain(|p| remap.contains_key(&p.docid));
+            for posting in postings.iter_mut() {
+                posting.docid = remap[&posting.docid];
+            }
+            if postings.is_empty() {
+                empty_coordinates.push(coordinate);
+            } else {
+                let max = postings.iter().fold(0_f32, |m, p| m.max(p.value.abs()));
+                self.list_max.insert(coordinate, max);
+                self.doc_freq.insert(coordinate, postings.len() as u32);
+            }
+        }
+        for coordinate in empty_coordinates {
+            self.inverted_index.remove(&coordinate);
+            self.list_max.remove(&coordinate);
+            self.doc_freq.remove(&coordinate);
+        }
+
+        self.doc_length = doc_length;
+        self.num_docs = next_docid;
+        self.tombstones.clear();
+        remap
+    }
+
+    /// Accumulates this coordinate's contribution into `scores`. When `candidates` is `Some`, only
+    /// postings whose docid is already in the set are scored; this is how `retrieve`'s
+    /// budget-driven early exit folds in a "non-essential" coordinate without letting it
+    /// introduce documents that the essential coordinates haven't already surfaced.
+    fn compute_dot_product(&self, coordinate: u32, query_value: f32, scores: &mut [f32], candidates: Option<&HashSet<u32>>) {
         match self.inverted_index.get(&coordinate) {
             None => {}
             Some(postings) => {
                 for posting in postings {
-                    scores[posting.docid as usize] += query_value * posting.value;
+                    if candidates.map_or(true, |c| c.contains(&posting.docid)) {
+                        scores[posting.docid as usize] += query_value * posting.value;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Accumulates this coordinate's BM25 contribution into `scores`, treating each posting's
+    /// value as a term frequency. `candidates` has the same meaning as in `compute_dot_product`.
+    fn compute_bm25(&self, coordinate: u32, query_value: f32, params: &Bm25Params, scores: &mut [f32], candidates: Option<&HashSet<u32>>) {
+        let postings = match self.inverted_index.get(&coordinate) {
+            None => return,
+            Some(postings) => postings,
+        };
+        let df = *self.doc_freq.get(&coordinate).unwrap_or(&0) as f32;
+        if df == 0_f32 {
+            return;
+        }
+        let idf = (((self.num_docs as f32 - df + 0.5) / (df + 0.5)) + 1_f32).ln();
+
+        for posting in postings {
+            if !candidates.map_or(true, |c| c.contains(&posting.docid)) {
+                continue;
+            }
+            let tf = posting.value;
+            let doc_length = self.doc_length[posting.docid as usize];
+            let norm = 1_f32 - params.b + params.b * doc_length / params.avg_doc_length;
+            let contribution = idf * (tf * (params.k1 + 1_f32)) / (tf + params.k1 * norm);
+            scores[posting.docid as usize] += query_value * contribution;
+        }
+    }
+
+    /// An upper bound on the contribution `coordinate` can make to any document's score under
+    /// `mode`, used by `retrieve`'s essential/non-essential pruning split to decide which
+    /// coordinates are safe to demote. For `InnerProduct` this is `|query_value| * list_max`, the
+    /// same bound `retrieve_wand` uses. For `Bm25` the raw `list_max` bound doesn't apply: a
+    /// coordinate's contribution is `idf * tf * (k1 + 1) / (tf + k1 * norm)`, which strictly
+    /// increases with `tf` and strictly decreases with `norm`, so it approaches but never reaches
+    /// `idf * (k1 + 1)` as `tf` grows or `norm` shrinks towards 0 (i.e. a very short document) --
+    /// regardless of how large `list_max` happens to be for this coordinate. A rare coordinate
+    /// (high `idf`) with a small `list_max` can therefore have a much larger true bound than its
+    /// `list_max` would suggest.
+    fn upper_bound(&self, coordinate: u32, query_value: f32, mode: ScoringMode) -> f32 {
+        match mode {
+            ScoringMode::InnerProduct => {
+                let list_max = *self.list_max.get(&coordinate).unwrap_or(&0_f32);
+                query_value.abs() * list_max
+            }
+            ScoringMode::Bm25 { k1, .. } => {
+                let df = *self.doc_freq.get(&coordinate).unwrap_or(&0) as f32;
+                if df == 0_f32 {
+                    return 0_f32;
                 }
+                let idf = (((self.num_docs as f32 - df + 0.5) / (df + 0.5)) + 1_f32).ln();
+                idf * (k1 + 1_f32) * query_value.abs()
             }
         }
     }
 
-    /// Returns the `top_k` documents according to the inner product score with the given query.
+    /// Returns the `top_k` documents according to the given `mode`'s score for the given query.
     ///
-    /// This function implements a basic coordinate-at-a-time algorithm to compute the inner product
+    /// This function implements a basic coordinate-at-a-time algorithm to compute document
     /// scores, followed by a heap-based algorithm to identify the top-k entries.
     ///
-    /// When `inner_product_budget` is provided, this function stops computing document scores when
-    /// the budget is exhausted. It then moves on to the sort operation. Note that, the time spent
-    /// on the sort operation is separate from the given time budget.
+    /// When `inner_product_budget` is `Some`, query coordinates are instead sorted in descending
+    /// order of `upper_bound` (the largest possible contribution each one can make under `mode`)
+    /// and scored one at a time. After each coordinate, once the top-k heap built from the
+    /// documents touched so far is full, this checks whether the combined upper bound of the
+    /// remaining, not-yet-scored coordinates can still exceed the heap's k-th best score. If it
+    /// can't, those coordinates are demoted to "non-essential": a document that hasn't been
+    /// touched by an essential coordinate can no longer reach the top-k no matter what the
+    /// non-essential coordinates contribute, so their posting lists are only consulted for
+    /// documents already touched, never to surface new ones (the classic MaxScore term-pruning
+    /// split). This is exact when posting and query values are non-negative; with signed values
+    /// (as `insert` otherwise allows) a skipped non-essential coordinate could in principle still
+    /// shift a touched document's score downward relative to an untouched one. `budget` itself is
+    /// kept as a backstop: if it runs out before the bound-based exit fires, scoring still stops
+    /// there, same as before, just no longer exact.
     pub fn retrieve(&self, query: &HashMap<u32, f32>,
                 top_k: usize,
-                inner_product_budget: Option<Duration>) -> Vec<SearchResult> {
+                inner_product_budget: Option<Duration>,
+                mode: ScoringMode) -> Vec<SearchResult> {
         // Create an array with the same size as the number of documents in the index.
         let mut scores = Vec::with_capacity(self.num_docs as usize);
         scores.resize(self.num_docs as usize, 0_f32);
 
+        let avg_doc_length = if self.num_docs == 0 {
+            0_f32
+        } else {
+            self.doc_length.iter().sum::<f32>() / self.num_docs as f32
+        };
+        let accumulate = |coordinate: u32, query_value: f32, scores: &mut [f32], candidates: Option<&HashSet<u32>>| {
+            match mode {
+                ScoringMode::InnerProduct => self.compute_dot_product(coordinate, query_value, scores, candidates),
+                ScoringMode::Bm25 { k1, b } =>
+                    self.compute_bm25(coordinate, query_value, &Bm25Params { k1, b, avg_doc_length }, scores, candidates),
+            }
+        };
+
         match inner_product_budget {
             None => {
                 // Simply traverse the index one coordinate at a time and accumulate partial scores.
                 for (&coordinate, &query_value) in query {
-                    self.compute_dot_product(coordinate, query_value, &mut scores);
+                    accumulate(coordinate, query_value, &mut scores, None);
                 }
             }
             Some(budget) => {
                 let mut time_left = Duration::from(budget);
+                let mut touched: HashSet<u32> = HashSet::new();
 
-                // Sort query coordinates by absolute value in descending order.
-                let mut query = query.iter()
-                    .map(|(k, v)| (*k, *v)).collect::<Vec<(u32, f32)>>();
-                query.sort_by(|(_, v1), (_, v2)| v2.abs().partial_cmp(&v1.abs()).unwrap_or(Equal));
+                // Sort query coordinates in descending order of the largest contribution each one
+                // can possibly make, so the coordinates most likely to matter are scored first.
+                let mut coordinates = query.iter()
+                    .map(|(&coordinate, &query_value)| {
+                        let bound = self.upper_bound(coordinate, query_value, mode);
+                        (coordinate, query_value, bound)
+                    })
+                    .collect::<Vec<(u32, f32, f32)>>();
+                coordinates.sort_by(|(_, _, b1), (_, _, b2)| b2.partial_cmp(b1).unwrap_or(Equal));
+
+                let mut remaining_bound: f32 = coordinates.iter().map(|(_, _, bound)| bound).sum();
+
+                for (i, &(coordinate, query_value, bound)) in coordinates.iter().enumerate() {
+                    if let Some(postings) = self.inverted_index.get(&coordinate) {
+                        for posting in postings {
+                            touched.insert(posting.docid);
+                        }
+                    }
 
-                // Traverse the inverted index one coordinate at a time and accumulate partial scores.
-                // Quit as soon as the time budget is exhausted.
-                for (coordinate, query_value) in query {
                     let scoring_time = Instant::now();
-                    self.compute_dot_product(coordinate, query_value, &mut scores);
+                    accumulate(coordinate, query_value, &mut scores, None);
                     let scoring_time = scoring_time.elapsed();
                     time_left = if time_left > scoring_time { time_left - scoring_time } else { Duration::ZERO };
+                    remaining_bound -= bound;
+
+                    if touched.len() >= top_k {
+                        let mut heap: BinaryHeap<Reverse<SearchResult>> = BinaryHeap::new();
+                        let mut threshold = f32::MIN;
+                        for &docid in &touched {
+                            let score = scores[docid as usize];
+                            if score > threshold && !self.tombstones.contains(&docid) {
+                                heap.push(Reverse(SearchResult { docid, score }));
+                                if heap.len() > top_k {
+                                    threshold = heap.pop().unwrap().0.score;
+                                }
+                            }
+                        }
+                        let kth_best = heap.peek().map_or(f32::MIN, |r| r.0.score);
+                        if heap.len() == top_k && remaining_bound <= kth_best {
+                            // None of the remaining coordinates, even summed, can lift an
+                            // untouched document above the current k-th best: fold them in as
+                            // non-essential, applying their contribution only to documents
+                            // already touched.
+                            for &(coordinate, query_value, _) in &coordinates[i + 1..] {
+                                accumulate(coordinate, query_value, &mut scores, Some(&touched));
+                            }
+                            break;
+                        }
+                    }
+
                     if time_left.is_zero() {
-                        break
+                        break;
                     }
                 }
             }
@@ -123,7 +431,7 @@ impl Index {
 
         let mut threshold = f32::MIN;
         for (docid, &score) in scores.iter().enumerate() {
-            if score > threshold {
+            if score > threshold && !self.tombstones.contains(&(docid as u32)) {
                 heap.push(Reverse(SearchResult { docid: docid as u32, score }));
                 if heap.len() > top_k {
                     threshold = heap.pop().unwrap().0.score;
@@ -134,6 +442,103 @@ impl Index {
         heap.into_sorted_vec().iter().map(|e| e.0).collect()
     }
 
+    /// Returns the `top_k` documents according to the inner product score with the given query,
+    /// using document-at-a-time traversal with WAND pruning instead of the coordinate-at-a-time
+    /// scan used by `retrieve`.
+    ///
+    /// Each live query coordinate gets a `PostingCursor` seeded with an upper bound on the
+    /// contribution any posting in its list can make (`|query_value| * list_max`). On every
+    /// step, cursors are sorted by current docid and walked in that order, accumulating upper
+    /// bounds until the running sum exceeds the current threshold (the k-th best score in the
+    /// heap, or `f32::MIN` until the heap is full); the cursor where this happens defines the
+    /// pivot docid, since no document before it can possibly beat the threshold. If every cursor
+    /// up to the pivot is already positioned on the pivot docid, that document is fully scored and
+    /// the cursors on it are advanced; otherwise the lagging cursors are skipped forward to the
+    /// pivot. Among documents that share at least one coordinate with the query this yields the
+    /// same top-k as `retrieve` (which of several documents tied at the k-th score is kept may
+    /// differ), while never fully scoring documents that provably cannot enter the heap.
+    /// Documents that share no coordinate with the query are never returned, whereas `retrieve`
+    /// includes them with a score of 0 when fewer than `top_k` touched documents score above 0.
+    pub fn retrieve_wand(&self, query: &HashMap<u32, f32>, top_k: usize) -> Vec<SearchResult> {
+        let mut cursors: Vec<PostingCursor> = query.iter()
+            .filter_map(|(&coordinate, &query_value)| {
+                let postings = self.inverted_index.get(&coordinate)?;
+                if postings.is_empty() {
+                    return None;
+                }
+                let list_max = *self.list_max.get(&coordinate).unwrap_or(&0_f32);
+                Some(PostingCursor::new(postings, query_value, list_max))
+            })
+            .collect();
+
+        let mut heap: BinaryHeap<Reverse<SearchResult>> = BinaryHeap::new();
+        let mut threshold = f32::MIN;
+
+        loop {
+            cursors.retain(|c| !c.is_exhausted());
+            if cursors.is_empty() {
+                break;
+            }
+            cursors.sort_by_key(|c| c.docid());
+
+            // Walk cursors in ascending docid order, accumulating upper bounds until they
+            // exceed the threshold. The cursor at which this happens is the pivot: no document
+            // strictly before its docid can possibly beat the k-th best score.
+            let mut bound = 0_f32;
+            let mut pivot = None;
+            for (i, cursor) in cursors.iter().enumerate() {
+                bound += cursor.max_contribution;
+                if bound > threshold {
+                    pivot = Some(i);
+                    break;
+                }
+            }
+            let pivot = match pivot {
+                Some(p) => p,
+                None => break, // No prefix of the live cursors can beat the threshold; done.
+            };
+            let pivot_docid = cursors[pivot].docid();
+
+            if cursors[0].docid() == pivot_docid {
+                // Cursors are sorted ascending, so the first one sharing the pivot docid implies
+                // every cursor up to the pivot does too: fully score the document.
+                let mut score = 0_f32;
+                for cursor in cursors.iter() {
+                    if cursor.docid() != pivot_docid {
+                        break;
+                    }
+                    score += cursor.query_value * cursor.value();
+                }
+                if score > threshold && !self.tombstones.contains(&pivot_docid) {
+                    heap.push(Reverse(SearchResult { docid: pivot_docid, score }));
+                    if heap.len() > top_k {
+                        heap.pop();
+                    }
+                    if heap.len() == top_k {
+                        if let Some(Reverse(kth_best)) = heap.peek() {
+                            threshold = kth_best.score;
+                        }
+                    }
+                }
+                for cursor in cursors.iter_mut() {
+                    if cursor.docid() != pivot_docid {
+                        break;
+                    }
+                    cursor.pos += 1;
+                }
+            } else {
+                // Not every leading cursor has reached the pivot yet; skip the lagging ones.
+                for cursor in cursors.iter_mut() {
+                    if cursor.docid() < pivot_docid {
+                        cursor.skip_to(pivot_docid);
+                    }
+                }
+            }
+        }
+
+        heap.into_sorted_vec().iter().map(|e| e.0).collect()
+    }
+
     /// save the index to a file
     pub fn save(&self, file: &mut std::fs::File) {
 
@@ -150,6 +555,96 @@ impl Index {
         let reader = BufReader::new(file);
         bincode::deserialize_from(reader).unwrap()
     }
+
+    /// Saves the index to a compact on-disk format: a small header, an offset table (one entry
+    /// per coordinate, giving its posting count, `max_abs_value`, and where its block sits in the
+    /// posting-list region), the posting-list region itself, and finally the tombstone set. Each
+    /// block holds that coordinate's postings delta+varint-encoded (see
+    /// `compressed::encode_posting_list`), with zstd applied on top of the whole block when
+    /// `use_zstd` is set. This is substantially smaller on disk than `save`'s raw bincode dump of
+    /// every `{docid, value}` pair, and the offset table lets `MmapIndex` seek straight to a
+    /// single list without touching the rest of the file (`load_compressed` still decodes them all).
+    /// Tombstones are persisted too, so a `delete`d docid saved this way stays deleted across a
+    /// save/reload (or a `SegmentedIndex` flush, which always goes through this path) rather than
+    /// reappearing until the next `compact`.
+    pub fn save_compressed(&self, file: &mut std::fs::File, use_zstd: bool) {
+        let mut offset_table = Vec::new();
+        let mut region = Vec::new();
+
+        for (&coordinate, postings) in &self.inverted_index {
+            let raw = compressed::encode_posting_list(postings);
+            let stored = compressed::maybe_compress(&raw, use_zstd);
+            let max_abs_value = *self.list_max.get(&coordinate).unwrap_or(&0_f32);
+
+            // Offset table entry: coordinate, count, max_abs_value, then where the (possibly
+            // compressed) block lives in `region` and how big it is compressed/uncompressed.
+            offset_table.extend_from_slice(&coordinate.to_le_bytes());
+            offset_table.extend_from_slice(&(postings.len() as u32).to_le_bytes());
+            offset_table.extend_from_slice(&max_abs_value.to_le_bytes());
+            offset_table.extend_from_slice(&(region.len() as u64).to_le_bytes());
+            offset_table.extend_from_slice(&(stored.len() as u64).to_le_bytes());
+            offset_table.extend_from_slice(&(raw.len() as u64).to_le_bytes());
+
+            region.extend_from_slice(&stored);
+        }
+
+        let mut header = Vec::new();
+        compressed::write_magic(&mut header);
+        header.extend_from_slice(&self.num_docs.to_le_bytes());
+        header.extend_from_slice(&(self.inverted_index.len() as u32).to_le_bytes());
+        header.push(use_zstd as u8);
+        header.extend_from_slice(&(region.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(self.tombstones.len() as u32).to_le_bytes());
+
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&header).expect("Failed to write header");
+        writer.write_all(&offset_table).expect("Failed to write offset table");
+        writer.write_all(&region).expect("Failed to write posting-list region");
+        for &docid in &self.tombstones {
+            writer.write_all(&docid.to_le_bytes()).expect("Failed to write tombstones");
+        }
+    }
+
+    /// Loads an index saved with `save_compressed`, decoding every posting list back into the
+    /// same in-memory representation `load` produces.
+    pub fn load_compressed(file: &std::fs::File) -> Index {
+        let mut reader = BufReader::new(file);
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).expect("Failed to read compressed index");
+
+        let (num_docs, num_lists, use_zstd, region_len, num_tombstones) = compressed::parse_header(&bytes);
+        let (entries, region_start) = compressed::parse_offset_table(&bytes, num_lists);
+
+        let mut inverted_index = HashMap::new();
+        let mut list_max = HashMap::new();
+        let mut doc_freq = HashMap::new();
+        let mut squared_length = vec![0_f32; num_docs as usize];
+
+        for entry in entries {
+            let offset = entry.offset as usize;
+            let stored_len = entry.stored_len as usize;
+            let stored = &bytes[region_start + offset..region_start + offset + stored_len];
+            let raw = compressed::maybe_decompress(stored, entry.raw_len, use_zstd);
+            let postings = compressed::decode_posting_list(&raw, entry.count as usize);
+
+            for posting in &postings {
+                squared_length[posting.docid as usize] += posting.value * posting.value;
+            }
+            doc_freq.insert(entry.coordinate, entry.count);
+            inverted_index.insert(entry.coordinate, postings);
+            list_max.insert(entry.coordinate, entry.max_abs_value);
+        }
+
+        // The compressed format doesn't store per-document length directly, but it's fully
+        // recoverable from the posting lists we just decoded.
+        let doc_length = squared_length.into_iter().map(f32::sqrt).collect();
+
+        let tombstones_start = region_start + region_len as usize;
+        let tombstones = compressed::parse_tombstones(&bytes, tombstones_start, num_tombstones)
+            .into_iter().collect();
+
+        Index { inverted_index, list_max, num_docs, tombstones, doc_length, doc_freq }
+    }
 }
 
 // To use the `{}` marker, the trait `fmt::Display` must be implemented
@@ -164,8 +659,9 @@ impl fmt::Display for Index {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-    use crate::index::Index;
+    use std::collections::{HashMap, HashSet};
+    use std::time::Duration;
+    use crate::index::{Index, ScoringMode, SearchResult};
 
     #[test]
     fn test_serde() {
@@ -187,4 +683,219 @@ mod tests {
         assert_eq!(ind.num_docs, ind_rec.num_docs);
         assert_eq!(ind.inverted_index, ind_rec.inverted_index);
     }
+
+    #[test]
+    fn test_retrieve_wand_matches_retrieve() {
+        let mut ind = Index::new();
+
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32), (5, 0.6)]));
+        ind.insert(&HashMap::from([(2_u32, 0.4_f32), (5, 0.9)]));
+        ind.insert(&HashMap::from([(1_u32, -0.3_f32), (2, 0.2), (5, 0.1)]));
+        ind.insert(&HashMap::from([(1_u32, 0.8_f32), (2, -0.5)]));
+
+        let query = HashMap::from([(1_u32, 0.4_f32), (2, 0.3), (5, 1.2)]);
+
+        let expected = ind.retrieve(&query, 3, None, ScoringMode::InnerProduct);
+        let actual = ind.retrieve_wand(&query, 3);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_save_load_compressed_roundtrip() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32), (5, 0.6)]));
+        ind.insert(&HashMap::from([(2_u32, 0.4_f32), (5, 0.9)]));
+        ind.insert(&HashMap::from([(1_u32, -0.3_f32), (2, 0.2), (5, 0.1)]));
+
+        for use_zstd in [false, true] {
+            let path = std::env::temp_dir()
+                .join(format!("linscan_test_compressed_{use_zstd}.bin"));
+
+            let mut file = std::fs::File::create(&path).unwrap();
+            ind.save_compressed(&mut file, use_zstd);
+            drop(file);
+
+            let file = std::fs::File::open(&path).unwrap();
+            let ind_rec = Index::load_compressed(&file);
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(ind.num_docs, ind_rec.num_docs);
+            assert_eq!(ind.inverted_index, ind_rec.inverted_index);
+            assert_eq!(ind.list_max, ind_rec.list_max);
+            assert_eq!(ind.tombstones, ind_rec.tombstones);
+        }
+    }
+
+    #[test]
+    fn test_save_load_compressed_roundtrip_preserves_tombstones() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.9_f32)]));
+        ind.delete(0);
+
+        let path = std::env::temp_dir().join("linscan_test_compressed_tombstones.bin");
+        let mut file = std::fs::File::create(&path).unwrap();
+        ind.save_compressed(&mut file, true);
+        drop(file);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let ind_rec = Index::load_compressed(&file);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(ind_rec.tombstones, HashSet::from([0]));
+
+        let query = HashMap::from([(1_u32, 1.0_f32)]);
+        let results = ind_rec.retrieve(&query, 2, None, ScoringMode::InnerProduct);
+        assert!(!results.iter().any(|r| r.docid == 0));
+    }
+
+    #[test]
+    fn test_delete_is_excluded_from_retrieve() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.9_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.6_f32)]));
+
+        let query = HashMap::from([(1_u32, 1.0_f32)]);
+        ind.delete(1);
+
+        let results = ind.retrieve(&query, 3, None, ScoringMode::InnerProduct);
+        assert!(!results.iter().any(|r| r.docid == 1));
+        assert_eq!(ind.retrieve_wand(&query, 3), results);
+    }
+
+    #[test]
+    fn test_update_reassigns_docid_and_tombstones_old_one() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.9_f32)]));
+
+        let new_docid = ind.update(0, &HashMap::from([(1_u32, 5.0_f32)]));
+        assert_eq!(new_docid, 2);
+
+        let query = HashMap::from([(1_u32, 1.0_f32)]);
+        let results = ind.retrieve(&query, 1, None, ScoringMode::InnerProduct);
+        assert_eq!(results, vec![SearchResult { docid: new_docid, score: 5.0 }]);
+    }
+
+    #[test]
+    fn test_compact_renumbers_docids_and_drops_tombstones() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.9_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.6_f32)]));
+        ind.delete(1);
+
+        let remap = ind.compact();
+        assert_eq!(remap.get(&0), Some(&0));
+        assert_eq!(remap.get(&2), Some(&1));
+        assert_eq!(remap.get(&1), None);
+        assert_eq!(ind.num_docs, 2);
+
+        let query = HashMap::from([(1_u32, 1.0_f32)]);
+        let mut results = ind.retrieve(&query, 2, None, ScoringMode::InnerProduct);
+        results.sort_by(|a, b| a.docid.cmp(&b.docid));
+        assert_eq!(
+            results,
+            vec![
+                SearchResult { docid: 0, score: 0.4 },
+                SearchResult { docid: 1, score: 0.6 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compact_rebuilds_doc_length_so_bm25_scores_dont_go_stale() {
+        let mut ind = Index::new();
+        // doc 1 has a much longer document length than docs 0 and 2, which otherwise carry
+        // byte-identical postings. Deleting and compacting doc 1 away must not leave doc 2's
+        // renumbered doc_length entry pointing at doc 1's stale length.
+        ind.insert(&HashMap::from([(1_u32, 1.0_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 1.0_f32), (2, 1.0), (3, 1.0), (4, 1.0)]));
+        ind.insert(&HashMap::from([(1_u32, 1.0_f32)]));
+        ind.delete(1);
+        ind.compact();
+
+        let query = HashMap::from([(1_u32, 1.0_f32)]);
+        let mode = ScoringMode::Bm25 { k1: 1.2, b: 0.75 };
+        let results = ind.retrieve(&query, 2, None, mode);
+        assert_eq!(results[0].score, results[1].score);
+    }
+
+    #[test]
+    fn test_bm25_scores_documents_with_higher_term_frequency_higher() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 1.0_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 5.0_f32)]));
+
+        let query = HashMap::from([(1_u32, 1.0_f32)]);
+        let results = ind.retrieve(&query, 2, None, ScoringMode::Bm25 { k1: 1.2, b: 0.75 });
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].docid, 1);
+        assert!(results[0].score > results[1].score);
+    }
+
+    #[test]
+    fn test_retrieve_budget_uses_bm25_specific_bound_not_list_max() {
+        // Coordinate 2 is rare (df=1 out of 53 docs), so its BM25 idf is high, even though its
+        // list_max (a raw max-tf bound only valid for InnerProduct) is no bigger than coordinate
+        // 1's. A pruning bound that used list_max for BM25 would rank coordinate 1 ahead of
+        // coordinate 2, demote coordinate 2 to non-essential before the true top document (2) is
+        // ever touched, and return docid 1 instead.
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 1.0_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 1.0_f32)]));
+        ind.insert(&HashMap::from([(2_u32, 1.0_f32)]));
+        for _ in 0..50 {
+            ind.insert(&HashMap::new());
+        }
+
+        let query = HashMap::from([(1_u32, 1.01_f32), (2, 1.0_f32)]);
+        let mode = ScoringMode::Bm25 { k1: 1.2, b: 0.75 };
+
+        let expected = ind.retrieve(&query, 1, None, mode);
+        let actual = ind.retrieve(&query, 1, Some(Duration::from_secs(3600)), mode);
+        assert_eq!(expected, actual);
+        assert_eq!(expected[0].docid, 2);
+    }
+
+    #[test]
+    fn test_retrieve_with_budget_matches_unbounded_with_nonnegative_values() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.9_f32), (2, 0.1), (3, 0.2)]));
+        ind.insert(&HashMap::from([(1_u32, 0.1_f32), (2, 0.9), (4, 0.3)]));
+        ind.insert(&HashMap::from([(2_u32, 0.2_f32), (3, 0.1), (4, 0.1)]));
+        ind.insert(&HashMap::from([(1_u32, 0.3_f32), (3, 0.8), (5, 0.4)]));
+        ind.insert(&HashMap::from([(5_u32, 0.9_f32)]));
+
+        let query = HashMap::from([(1_u32, 0.8_f32), (2, 0.2), (3, 0.1), (5, 0.05_f32)]);
+
+        let expected = ind.retrieve(&query, 2, None, ScoringMode::InnerProduct);
+        let actual = ind.retrieve(&query, 2, Some(Duration::from_secs(3600)), ScoringMode::InnerProduct);
+
+        // The unbounded and budgeted paths accumulate the same f32 contributions in different
+        // orders (unsorted HashMap iteration vs. descending by bound), so the docids and ranking
+        // must agree but the scores can differ by last-ULP float noise.
+        assert_eq!(expected.len(), actual.len());
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            assert_eq!(e.docid, a.docid);
+            assert!((e.score - a.score).abs() < 1e-5, "{} vs {}", e.score, a.score);
+        }
+    }
+
+    #[test]
+    fn test_retrieve_with_budget_respects_tombstones() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.9_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.6_f32)]));
+
+        let query = HashMap::from([(1_u32, 1.0_f32)]);
+        ind.delete(1);
+
+        let results = ind.retrieve(&query, 3, Some(Duration::from_secs(3600)), ScoringMode::InnerProduct);
+        assert!(!results.iter().any(|r| r.docid == 1));
+        assert_eq!(ind.retrieve(&query, 3, None, ScoringMode::InnerProduct), results);
+    }
 }
@@ -3,7 +3,10 @@ use std::time::Duration;
 use pyo3::prelude::*;
 use rayon::prelude::*;
 
+mod compressed;
 mod index;
+mod mmap_index;
+mod segment;
 
 #[pyclass]
 struct LinscanIndex {
@@ -21,6 +24,15 @@ fn ms_to_duration(ms_opt: Option<f32>) -> Option<Duration> {
     ms_opt.map(|ms| Duration::from_secs_f32(ms / 1000_f32))
 }
 
+// picks the scoring mode for a retrieve call: BM25 if both k1 and b are given, inner product
+// otherwise.
+fn scoring_mode(k1: Option<f32>, b: Option<f32>) -> index::ScoringMode {
+    match (k1, b) {
+        (Some(k1), Some(b)) => index::ScoringMode::Bm25 { k1, b },
+        _ => index::ScoringMode::InnerProduct,
+    }
+}
+
 #[pymethods]
 impl LinscanIndex {
     // creates a new empty index.
@@ -47,22 +59,56 @@ impl LinscanIndex {
     }
 
     // search for the top_k, given a single query.
-    pub fn retrieve(&mut self, query: HashMap<u32, f32>, top_k: usize, inner_product_budget_ms: Option<f32>) -> Vec<u32> {
+    // if both k1 and b are supplied, documents are scored with BM25 instead of inner product.
+    #[pyo3(signature = (query, top_k, inner_product_budget_ms=None, k1=None, b=None))]
+    pub fn retrieve(&mut self, query: HashMap<u32, f32>, top_k: usize, inner_product_budget_ms: Option<f32>, k1: Option<f32>, b: Option<f32>) -> Vec<u32> {
 
-        let r = self.index.retrieve(&query, top_k, ms_to_duration(inner_product_budget_ms));
+        let r = self.index.retrieve(&query, top_k, ms_to_duration(inner_product_budget_ms), scoring_mode(k1, b));
         r.into_iter().map(|f| f.docid).collect()
     }
 
     // search for the top_k, given a collection of queries. Queries are issued in parallel using rayon's par_iter.
-    pub fn retrieve_parallel(&mut self, queries: Vec<HashMap<u32, f32>>, top_k: usize, inner_product_budget_ms: Option<f32>) -> Vec<Vec<u32>> {
+    // if both k1 and b are supplied, documents are scored with BM25 instead of inner product.
+    #[pyo3(signature = (queries, top_k, inner_product_budget_ms=None, k1=None, b=None))]
+    pub fn retrieve_parallel(&mut self, queries: Vec<HashMap<u32, f32>>, top_k: usize, inner_product_budget_ms: Option<f32>, k1: Option<f32>, b: Option<f32>) -> Vec<Vec<u32>> {
 
+        let mode = scoring_mode(k1, b);
         queries.par_iter().map(|q|
-            self.index.retrieve(&q, top_k, ms_to_duration(inner_product_budget_ms))
+            self.index.retrieve(&q, top_k, ms_to_duration(inner_product_budget_ms), mode)
                 .into_iter().map(|f| f.docid).collect()
         ).collect()
 
     }
 
+    // search for the top_k using document-at-a-time WAND pruning instead of the
+    // coordinate-at-a-time scan `retrieve` uses. Always scores by inner product.
+    pub fn retrieve_wand(&mut self, query: HashMap<u32, f32>, top_k: usize) -> Vec<u32> {
+        self.index.retrieve_wand(&query, top_k).into_iter().map(|f| f.docid).collect()
+    }
+
+    // marks a docid as deleted. Its postings stay in place until the next `compact`.
+    pub fn delete(&mut self, docid: u32) {
+        self.index.delete(docid);
+    }
+
+    // replaces the document at `docid` with `newdoc`, returning the new docid it was reinserted
+    // under.
+    pub fn update(&mut self, docid: u32, newdoc: HashMap<u32, f32>) -> u32 {
+        self.index.update(docid, &newdoc)
+    }
+
+    // physically drops tombstoned postings and renumbers the surviving docids contiguously from
+    // 0, returning a map from old docid to new docid.
+    pub fn compact(&mut self) -> HashMap<u32, u32> {
+        self.index.compact()
+    }
+
+    // saves the index to `path` in the compact on-disk format `LinscanMmapIndex` reads.
+    pub fn save_compressed(&self, path: String, use_zstd: bool) {
+        let mut file = std::fs::File::create(&path).expect("Failed to create index file");
+        self.index.save_compressed(&mut file, use_zstd);
+    }
+
     // this defines the out of the >str(index) in python
     fn __str__(&self) -> PyResult<String> {
         Ok(self.index.to_string())
@@ -75,11 +121,83 @@ impl LinscanIndex {
 }
 
 
+#[pyclass]
+struct LinscanSegmentedIndex {
+    index: segment::SegmentedIndex,
+}
+
+#[pymethods]
+impl LinscanSegmentedIndex {
+    // creates a new segmented index that flushes buffered documents to segment files under
+    // `dir` once the in-memory buffer reaches `flush_threshold` documents.
+    #[new]
+    pub fn new(dir: String, flush_threshold: u32) -> LinscanSegmentedIndex {
+        LinscanSegmentedIndex {
+            index: segment::SegmentedIndex::new(dir, flush_threshold),
+        }
+    }
+
+    // insert a new document into the index.
+    pub fn insert(&mut self, newdoc: HashMap<u32, f32>) {
+        self.index.insert(&newdoc);
+    }
+
+    // flushes the in-memory buffer to a new segment file, if it holds any documents.
+    pub fn flush(&mut self) {
+        self.index.flush();
+    }
+
+    // k-way merges the live segments at `segment_indices` (a contiguous, ascending run of
+    // indices into the current segment list) into a single segment file.
+    pub fn merge(&mut self, segment_indices: Vec<usize>) {
+        self.index.merge(&segment_indices);
+    }
+
+    // search for the top_k documents across every live segment and the in-memory buffer.
+    pub fn retrieve(&self, query: HashMap<u32, f32>, top_k: usize) -> Vec<u32> {
+        self.index.retrieve(&query, top_k).into_iter().map(|f| f.docid).collect()
+    }
+
+    pub fn num_docs(&self) -> u32 {
+        self.index.num_docs()
+    }
+
+    pub fn num_segments(&self) -> usize {
+        self.index.num_segments()
+    }
+}
+
+#[pyclass]
+struct LinscanMmapIndex {
+    index: mmap_index::MmapIndex,
+}
+
+#[pymethods]
+impl LinscanMmapIndex {
+    // memory-maps the index file at `path`, which must have been written by
+    // `LinscanIndex::save_compressed`.
+    #[new]
+    pub fn new(path: String) -> LinscanMmapIndex {
+        let file = std::fs::File::open(&path).expect("Failed to open index file");
+        LinscanMmapIndex {
+            index: mmap_index::MmapIndex::open(&file),
+        }
+    }
+
+    // search for the top_k documents according to the inner product score with the given query,
+    // decoding only the posting lists the query's coordinates actually touch.
+    pub fn retrieve(&self, query: HashMap<u32, f32>, top_k: usize) -> Vec<u32> {
+        self.index.retrieve(&query, top_k).into_iter().map(|f| f.docid).collect()
+    }
+}
+
 /// A Python module implemented in Rust. The name of this function must match
 /// the `lib.name` setting in the `Cargo.toml`, else Python will not be able to
 /// import the module.
 #[pymodule]
 fn linscan(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<LinscanIndex>()?;
+    m.add_class::<LinscanSegmentedIndex>()?;
+    m.add_class::<LinscanMmapIndex>()?;
     Ok(())
 }
\ No newline at end of file
@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 
+mod compressed;
 mod index;
+mod mmap_index;
+mod segment;
 
 fn main() {
-    let mut ind = index::Index::new(false);
+    let mut ind = index::Index::new();
     let v1 = HashMap::from([(1_u32, 0.4_f32), (5, 0.6)]);
     let v2 = HashMap::from([(2_u32, 0.4_f32), (5, 0.9)]);
     let q1 = HashMap::from([(13_u32, 0.4_f32), (5, 1.2)]);
@@ -12,7 +15,7 @@ fn main() {
 
     println!("Index built: {}", ind);
 
-    let r = ind.retrieve(&q1, 4, None);
+    let r = ind.retrieve(&q1, 4, None, index::ScoringMode::InnerProduct);
     println!("{:?}", &r);
 
 
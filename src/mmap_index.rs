@@ -0,0 +1,134 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::File;
+
+use memmap2::Mmap;
+
+use crate::compressed::{self, OffsetEntry, PostingListDecoder};
+use crate::index::SearchResult;
+
+/// A read-only, memory-mapped view over an index saved with `Index::save_compressed`.
+///
+/// `Index::load_compressed` eagerly decodes every posting list into memory up front; this type
+/// instead mmaps the whole file and parses only the small coordinate -> offset table into memory,
+/// leaving the posting-list region itself untouched until a query actually needs a particular
+/// list, at which point `compute_dot_product` decodes just that one block. Because the mapping is
+/// read-only, many independent query processes can share the same physical pages for one large
+/// index rather than each paying to deserialize their own copy.
+pub struct MmapIndex {
+    mmap: Mmap,
+    num_docs: u32,
+    use_zstd: bool,
+    region_start: usize,
+    offsets: HashMap<u32, OffsetEntry>,
+    /// Docids tombstoned at save time. Parsed eagerly (it's a flat `u32` list, cheap relative to
+    /// the posting lists) so `retrieve` can skip them exactly as `Index::retrieve` does.
+    tombstones: HashSet<u32>,
+}
+
+impl MmapIndex {
+    /// Memory-maps `file` and parses its header, offset table, and tombstone set. `file` must
+    /// have been written by `Index::save_compressed`.
+    pub fn open(file: &File) -> MmapIndex {
+        let mmap = unsafe { Mmap::map(file).expect("Failed to mmap index file") };
+
+        let (num_docs, num_lists, use_zstd, region_len, num_tombstones) = compressed::parse_header(&mmap);
+        let (entries, region_start) = compressed::parse_offset_table(&mmap, num_lists);
+        let offsets = entries.into_iter().map(|e| (e.coordinate, e)).collect();
+        let tombstones = compressed::parse_tombstones(&mmap, region_start + region_len as usize, num_tombstones)
+            .into_iter().collect();
+
+        MmapIndex { mmap, num_docs, use_zstd, region_start, offsets, tombstones }
+    }
+
+    /// Decodes and accumulates one coordinate's contribution into `scores`, without ever
+    /// materializing the coordinate's posting list as a `Vec<Posting>`.
+    fn compute_dot_product(&self, coordinate: u32, query_value: f32, scores: &mut [f32]) {
+        let entry = match self.offsets.get(&coordinate) {
+            None => return,
+            Some(entry) => entry,
+        };
+
+        let stored = &self.mmap[self.region_start + entry.offset as usize
+            ..self.region_start + entry.offset as usize + entry.stored_len as usize];
+
+        // A zstd block has to be decompressed as a whole, but the decompressed bytes are still
+        // only decoded one posting at a time below.
+        let raw = compressed::maybe_decompress(stored, entry.raw_len, self.use_zstd);
+        for posting in PostingListDecoder::new(&raw, entry.count as usize) {
+            scores[posting.docid as usize] += query_value * posting.value;
+        }
+    }
+
+    /// Returns the `top_k` documents according to the inner-product score with the given query,
+    /// decoding only the posting lists the query's coordinates actually touch.
+    pub fn retrieve(&self, query: &HashMap<u32, f32>, top_k: usize) -> Vec<SearchResult> {
+        let mut scores = vec![0_f32; self.num_docs as usize];
+        for (&coordinate, &query_value) in query {
+            self.compute_dot_product(coordinate, query_value, &mut scores);
+        }
+
+        let mut heap: BinaryHeap<Reverse<SearchResult>> = BinaryHeap::new();
+        let mut threshold = f32::MIN;
+        for (docid, &score) in scores.iter().enumerate() {
+            if score > threshold && !self.tombstones.contains(&(docid as u32)) {
+                heap.push(Reverse(SearchResult { docid: docid as u32, score }));
+                if heap.len() > top_k {
+                    threshold = heap.pop().unwrap().0.score;
+                }
+            }
+        }
+
+        heap.into_sorted_vec().iter().map(|e| e.0).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::index::{Index, ScoringMode};
+    use crate::mmap_index::MmapIndex;
+
+    #[test]
+    fn test_mmap_index_matches_in_memory_retrieve() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32), (5, 0.6)]));
+        ind.insert(&HashMap::from([(2_u32, 0.4_f32), (5, 0.9)]));
+        ind.insert(&HashMap::from([(1_u32, -0.3_f32), (2, 0.2), (5, 0.1)]));
+
+        let path = std::env::temp_dir().join("linscan_test_mmap_index.bin");
+        let mut file = std::fs::File::create(&path).unwrap();
+        ind.save_compressed(&mut file, true);
+        drop(file);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let mmap_index = MmapIndex::open(&file);
+        std::fs::remove_file(&path).unwrap();
+
+        let query = HashMap::from([(1_u32, 0.4_f32), (2, 0.3), (5, 1.2)]);
+        let expected = ind.retrieve(&query, 3, None, ScoringMode::InnerProduct);
+        let actual = mmap_index.retrieve(&query, 3);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_mmap_index_excludes_tombstoned_docs() {
+        let mut ind = Index::new();
+        ind.insert(&HashMap::from([(1_u32, 0.4_f32)]));
+        ind.insert(&HashMap::from([(1_u32, 0.9_f32)]));
+        ind.delete(0);
+
+        let path = std::env::temp_dir().join("linscan_test_mmap_index_tombstones.bin");
+        let mut file = std::fs::File::create(&path).unwrap();
+        ind.save_compressed(&mut file, true);
+        drop(file);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let mmap_index = MmapIndex::open(&file);
+        std::fs::remove_file(&path).unwrap();
+
+        let query = HashMap::from([(1_u32, 1.0_f32)]);
+        let results = mmap_index.retrieve(&query, 2);
+        assert!(!results.iter().any(|r| r.docid == 0));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+
+use crate::index::{Index, ScoringMode, SearchResult};
+
+/// Metadata for one immutable on-disk segment: where it lives, and the contiguous range of
+/// global docids it covers (every docid stored inside the segment file itself is local, starting
+/// from 0, so `base_docid` is added back on at query time).
+struct SegmentMeta {
+    path: PathBuf,
+    base_docid: u32,
+    num_docs: u32,
+}
+
+/// An out-of-core index builder, modeled on the classic "buffer in memory, flush to an immutable
+/// segment file, merge segments later" design used by fingertips and most LSM-style search
+/// engines. `insert` only ever touches an in-memory `Index` buffer; once that buffer reaches
+/// `flush_threshold` documents it is written out to its own segment file (compressed, via
+/// `Index::save_compressed`) and a fresh buffer takes over. This means the corpus as a whole
+/// never has to fit in RAM, only one buffer's worth of it at a time.
+pub struct SegmentedIndex {
+    dir: PathBuf,
+    flush_threshold: u32,
+    buffer: Index,
+    buffer_base_docid: u32,
+    segments: Vec<SegmentMeta>,
+    next_segment_id: u32,
+}
+
+impl SegmentedIndex {
+    /// Creates a new segmented index that flushes buffered documents to segment files under
+    /// `dir` once the in-memory buffer reaches `flush_threshold` documents.
+    pub fn new(dir: impl Into<PathBuf>, flush_threshold: u32) -> SegmentedIndex {
+        SegmentedIndex {
+            dir: dir.into(),
+            flush_threshold,
+            buffer: Index::new(),
+            buffer_base_docid: 0,
+            segments: Vec::new(),
+            next_segment_id: 0,
+        }
+    }
+
+    /// The total number of documents inserted so far, across flushed segments and the buffer.
+    pub fn num_docs(&self) -> u32 {
+        self.buffer_base_docid + self.buffer.num_docs()
+    }
+
+    /// The number of live, flushed segment files (not counting the in-memory buffer).
+    pub fn num_segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Inserts a document, assigning it the next global docid. Flushes the in-memory buffer to a
+    /// new segment file once it reaches `flush_threshold` documents.
+    pub fn insert(&mut self, document: &HashMap<u32, f32>) {
+        self.buffer.insert(document);
+        if self.buffer.num_docs() >= self.flush_threshold {
+            self.flush();
+        }
+    }
+
+    /// Flushes the current in-memory buffer to a new immutable segment file, if it holds any
+    /// documents. A no-op otherwise.
+    pub fn flush(&mut self) {
+        if self.buffer.num_docs() == 0 {
+            return;
+        }
+
+        let path = self.dir.join(format!("segment-{}.lsc", self.next_segment_id));
+        let mut file = File::create(&path).expect("Failed to create segment file");
+        self.buffer.save_compressed(&mut file, true);
+
+        self.segments.push(SegmentMeta {
+            path,
+            base_docid: self.buffer_base_docid,
+            num_docs: self.buffer.num_docs(),
+        });
+
+        self.buffer_base_docid += self.buffer.num_docs();
+        self.next_segment_id += 1;
+        self.buffer = Index::new();
+    }
+
+    /// k-way merges the live segments at `segment_indices` (a contiguous, ascending run of
+    /// indices into the current segment list) into a single new segment file, remapping their
+    /// docids into one contiguous range. The merged segments' files are deleted from disk and
+    /// replaced in-place by the new one, keeping the live segment count down as more data is
+    /// flushed over time.
+    ///
+    /// `segment_indices` must be contiguous (e.g. `&[1, 2, 3]`, never `&[0, 2]`): the new
+    /// segment's `base_docid` is taken from the first index and every absorbed segment's docids
+    /// are shifted by a running counter, which only lines up with the surrounding segments'
+    /// untouched `base_docid`s when no segment in between is skipped.
+    pub fn merge(&mut self, segment_indices: &[usize]) {
+        assert!(!segment_indices.is_empty(), "merge requires at least one segment");
+        assert!(
+            segment_indices.windows(2).all(|w| w[1] == w[0] + 1),
+            "segment_indices must be a contiguous, ascending run"
+        );
+
+        let base_docid = self.segments[segment_indices[0]].base_docid;
+        let mut merged = Index::new();
+        let mut total_docs = 0_u32;
+
+        // Absorb segments in order: each one's postings are shifted by however many docs have
+        // already been folded into `merged`, which keeps every coordinate's combined posting
+        // list sorted in ascending docid order.
+        for &i in segment_indices {
+            let file = File::open(&self.segments[i].path).expect("Failed to open segment file");
+            let segment = Index::load_compressed(&file);
+            merged.absorb(&segment, total_docs);
+            total_docs += self.segments[i].num_docs;
+        }
+
+        let new_path = self.dir.join(format!("segment-{}.lsc", self.next_segment_id));
+        self.next_segment_id += 1;
+        let mut file = File::create(&new_path).expect("Failed to create segment file");
+        merged.save_compressed(&mut file, true);
+
+        for &i in segment_indices.iter().rev() {
+            let meta = self.segments.remove(i);
+            let _ = std::fs::remove_file(&meta.path);
+        }
+
+        self.segments.insert(segment_indices[0], SegmentMeta {
+            path: new_path,
+            base_docid,
+            num_docs: total_docs,
+        });
+    }
+
+    /// Retrieves the `top_k` documents across every live segment and the in-memory buffer.
+    /// Each is queried independently (so no more than one segment's posting lists need to be
+    /// loaded at a time) and their per-segment top-k results, with docids shifted back into the
+    /// global space, are merged into one overall top-k.
+    pub fn retrieve(&self, query: &HashMap<u32, f32>, top_k: usize) -> Vec<SearchResult> {
+        let mut results = Vec::new();
+
+        for meta in &self.segments {
+            let file = File::open(&meta.path).expect("Failed to open segment file");
+            let segment = Index::load_compressed(&file);
+            results.extend(
+                segment.retrieve(query, top_k, None, ScoringMode::InnerProduct).into_iter()
+                    .map(|r| SearchResult { docid: r.docid + meta.base_docid, score: r.score }),
+            );
+        }
+
+        results.extend(
+            self.buffer.retrieve(query, top_k, None, ScoringMode::InnerProduct).into_iter()
+                .map(|r| SearchResult { docid: r.docid + self.buffer_base_docid, score: r.score }),
+        );
+
+        results.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        results.truncate(top_k);
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::index::ScoringMode;
+    use crate::segment::SegmentedIndex;
+
+    #[test]
+    fn test_segmented_index_matches_single_index() {
+        let dir = std::env::temp_dir().join("linscan_test_segments");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut segmented = SegmentedIndex::new(&dir, 2);
+        let mut plain = crate::index::Index::new();
+
+        let docs = [
+            HashMap::from([(1_u32, 0.4_f32), (5, 0.6)]),
+            HashMap::from([(2_u32, 0.4_f32), (5, 0.9)]),
+            HashMap::from([(1_u32, -0.3_f32), (2, 0.2), (5, 0.1)]),
+            HashMap::from([(1_u32, 0.8_f32), (2, -0.5)]),
+            HashMap::from([(5_u32, 1.0_f32)]),
+        ];
+        for doc in &docs {
+            segmented.insert(doc);
+            plain.insert(doc);
+        }
+        segmented.flush();
+
+        assert_eq!(segmented.num_docs(), plain.num_docs());
+        assert!(segmented.num_segments() >= 2);
+
+        let query = HashMap::from([(1_u32, 0.4_f32), (2, 0.3), (5, 1.2)]);
+        let mut expected = plain.retrieve(&query, 3, None, ScoringMode::InnerProduct);
+        let mut actual = segmented.retrieve(&query, 3);
+        expected.sort_by(|a, b| a.docid.cmp(&b.docid));
+        actual.sort_by(|a, b| a.docid.cmp(&b.docid));
+        assert_eq!(expected, actual);
+
+        // Merge every live segment into one and check retrieval still agrees.
+        let all_segments: Vec<usize> = (0..segmented.num_segments()).collect();
+        segmented.merge(&all_segments);
+        assert_eq!(segmented.num_segments(), 1);
+
+        let mut actual = segmented.retrieve(&query, 3);
+        actual.sort_by(|a, b| a.docid.cmp(&b.docid));
+        assert_eq!(expected, actual);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}